@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bollard::container::{
+    Config, CreateContainerOptions, LogOutput, LogsOptions, StartContainerOptions,
+    WaitContainerOptions,
+};
+use bollard::image::CreateImageOptions;
+use bollard::models::{HostConfig, PortBinding};
+use bollard::Docker;
+use futures_util::stream::StreamExt;
+
+use crate::docker::hash::fnv1a;
+use crate::docker::{shell_interpolate, DockerRun};
+
+/// Talk to the Docker/Podman daemon directly over its HTTP API rather than
+/// shelling out to a CLI binary - useful when only a remote API endpoint is
+/// reachable, or when no `docker`/`podman` executable is on `PATH` at all.
+pub struct ApiBackend {
+    docker: Docker,
+}
+
+impl ApiBackend {
+    pub fn connect(endpoint: &str) -> Result<Self, Box<dyn Error>> {
+        let docker = if endpoint.starts_with("unix://") {
+            Docker::connect_with_unix_defaults()?
+        } else {
+            Docker::connect_with_http_defaults()?
+        };
+
+        Ok(ApiBackend { docker })
+    }
+
+    /// Translate `dr`'s fields into a container-create request, pulling the
+    /// image if it's missing, start the container, stream its logs back to
+    /// stdout/stderr, and return its exit code.
+    pub async fn run(
+        &self,
+        dr: &DockerRun,
+        kv: &HashMap<String, String>,
+    ) -> Result<i32, Box<dyn Error>> {
+        if let Some(extra_flags) = &dr.extra_flags {
+            if !extra_flags.is_empty() {
+                return Err(format!(
+                    "extra_flags {:?} have no Engine API equivalent and aren't \
+                     supported by the API backend; use the CLI backend instead",
+                    extra_flags
+                )
+                .into());
+            }
+        }
+
+        let image = shell_interpolate(&dr.image, kv)?;
+        self.pull_if_missing(&image).await?;
+
+        let env = self.build_env(dr, kv)?;
+        let ports = dr
+            .ports
+            .as_ref()
+            .map(|ports| {
+                ports
+                    .iter()
+                    .map(|p| shell_interpolate(p, kv))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+        let exposed_ports = ports.as_ref().map(|ports| {
+            ports
+                .iter()
+                .filter_map(|p| p.split(':').next_back())
+                .map(|container_port| (container_port.to_string(), HashMap::new()))
+                .collect()
+        });
+
+        let port_bindings = ports.as_ref().map(|ports| {
+            ports
+                .iter()
+                .filter_map(|p| {
+                    let mut parts = p.rsplitn(2, ':');
+                    let container_port = parts.next()?.to_string();
+                    let host_port = parts.next().map(str::to_string);
+                    Some((
+                        container_port,
+                        Some(vec![PortBinding {
+                            host_ip: None,
+                            host_port,
+                        }]),
+                    ))
+                })
+                .collect()
+        });
+
+        let binds = dr
+            .volumes
+            .as_ref()
+            .map(|volumes| {
+                volumes
+                    .iter()
+                    .map(|v| shell_interpolate(v, kv))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+
+        // Every field the CLI backend interpolates gets the same treatment
+        // here, so a config relying on `${VAR}`/`$(...)` behaves the same
+        // regardless of which backend runs it.
+        let entrypoint = dr
+            .entrypoint
+            .as_ref()
+            .map(|e| shell_interpolate(e, kv))
+            .transpose()?;
+        let cmd = dr
+            .command
+            .as_ref()
+            .map(|cmds| {
+                cmds.iter()
+                    .map(|c| shell_interpolate(c, kv))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+        let user = dr.user.as_ref().map(|u| shell_interpolate(u, kv)).transpose()?;
+        let network_mode = dr
+            .network
+            .as_ref()
+            .map(|n| shell_interpolate(n, kv))
+            .transpose()?;
+
+        let config = Config {
+            image: Some(image.clone()),
+            entrypoint: entrypoint.map(|e| vec![e]),
+            cmd,
+            env: Some(env),
+            exposed_ports,
+            user,
+            tty: dr.tty,
+            attach_stdin: dr.interactive,
+            host_config: Some(HostConfig {
+                binds,
+                port_bindings,
+                network_mode,
+                auto_remove: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let name = container_name(&image);
+        let created = self
+            .docker
+            .create_container(Some(CreateContainerOptions { name, platform: None }), config)
+            .await?;
+
+        self.docker
+            .start_container(&created.id, None::<StartContainerOptions<String>>)
+            .await?;
+
+        let mut logs = self.docker.logs(
+            &created.id,
+            Some(LogsOptions::<String> {
+                follow: true,
+                stdout: true,
+                stderr: true,
+                ..Default::default()
+            }),
+        );
+
+        // Keep stdout/stderr separate, same as the CLI backend (which just
+        // inherits the child's own fds), so piping either one behaves the
+        // same regardless of which backend ran the container.
+        while let Some(chunk) = logs.next().await {
+            match chunk? {
+                LogOutput::StdErr { message } => eprint!("{}", String::from_utf8_lossy(&message)),
+                LogOutput::StdOut { message } => print!("{}", String::from_utf8_lossy(&message)),
+                LogOutput::StdIn { message } | LogOutput::Console { message } => {
+                    print!("{}", String::from_utf8_lossy(&message))
+                }
+            }
+        }
+
+        let mut waits = self.docker.wait_container(
+            &created.id,
+            None::<WaitContainerOptions<String>>,
+        );
+
+        let exit_code = match waits.next().await {
+            Some(Ok(wait)) => wait.status_code as i32,
+            Some(Err(err)) => return Err(err.into()),
+            None => 0,
+        };
+
+        Ok(exit_code)
+    }
+
+    async fn pull_if_missing(&self, image: &str) -> Result<(), Box<dyn Error>> {
+        if self.docker.inspect_image(image).await.is_ok() {
+            return Ok(());
+        }
+
+        let mut pull = self.docker.create_image(
+            Some(CreateImageOptions {
+                from_image: image,
+                ..Default::default()
+            }),
+            None,
+            None,
+        );
+
+        while let Some(progress) = pull.next().await {
+            progress?;
+        }
+
+        Ok(())
+    }
+
+    fn build_env(
+        &self,
+        dr: &DockerRun,
+        kv: &HashMap<String, String>,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut env = vec![];
+
+        if let Some(envs) = &dr.envs {
+            for (k, v) in envs {
+                env.push(shell_interpolate(&format!("{}={}", k, v), kv)?);
+            }
+        }
+
+        if let Some(env_file) = &dr.env_file {
+            let env_file = shell_interpolate(&env_file.display().to_string(), kv)?;
+            for line in fs::read_to_string(&env_file)?.lines() {
+                if !line.trim().is_empty() && !line.trim_start().starts_with('#') {
+                    env.push(line.to_string());
+                }
+            }
+        }
+
+        Ok(env)
+    }
+}
+
+/// Blocking entry point for callers (the rest of this crate is sync): spin
+/// up a short-lived Tokio runtime, connect, and run `dr` to completion.
+pub fn run_blocking(
+    endpoint: &str,
+    dr: &DockerRun,
+    kv: &HashMap<String, String>,
+) -> Result<i32, Box<dyn Error>> {
+    let backend = ApiBackend::connect(endpoint)?;
+    tokio::runtime::Runtime::new()?.block_on(backend.run(dr, kv))
+}
+
+/// Per-run container name. Mixes in the process id and current time (not
+/// just the image) so two runs of the same image back-to-back - the
+/// common case - don't collide on an identical name; `auto_remove` on the
+/// `HostConfig` (the API equivalent of the CLI backend's `--rm`) then
+/// cleans the container up once it exits.
+fn container_name(image: &str) -> String {
+    let now_nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let entropy = format!("{}-{}-{}", std::process::id(), now_nanos, image);
+
+    format!("doa-{:016x}", fnv1a(&[entropy.as_bytes()]))
+}
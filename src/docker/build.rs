@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::docker::hash::fnv1a;
+use crate::docker::Engine;
+
+/// Prefix for the deterministic tag generated for images doa builds itself,
+/// so they're easy to recognize (and eventually prune) on the host.
+const BUILD_TAG_PREFIX: &str = "doa-build";
+
+/// Preprocess a Dockerfile, splicing in any `INCLUDE: <path>` directives
+/// (dockerfile-plus style) so common base stanzas can be shared across
+/// configs without needing a registry image. Includes are resolved relative
+/// to the including file's directory and may themselves contain includes.
+pub fn preprocess_dockerfile(path: &Path) -> Result<String, Box<dyn Error>> {
+    let contents = fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+    let mut spliced = String::new();
+    for line in contents.lines() {
+        match line.trim().strip_prefix("INCLUDE:") {
+            Some(include_path) => {
+                let include_path = dir.join(include_path.trim());
+                spliced.push_str(&preprocess_dockerfile(&include_path)?);
+                spliced.push('\n');
+            }
+            None => {
+                spliced.push_str(line);
+                spliced.push('\n');
+            }
+        }
+    }
+
+    Ok(spliced)
+}
+
+/// Derive a deterministic tag for a build, so repeated builds of the same
+/// dockerfile/context/build-args combination overwrite the same image
+/// rather than accumulating untagged layers.
+fn tag_for(dockerfile: &Path, context: &Path, build_args: &[(String, String)]) -> String {
+    let dockerfile = dockerfile.to_string_lossy();
+    let context = context.to_string_lossy();
+
+    // `build_args` arrives as whatever order the caller's map iterated in,
+    // which isn't stable across processes - sort by key first so the hash
+    // (and therefore the tag) only depends on the config's actual content.
+    let mut sorted_args: Vec<&(String, String)> = build_args.iter().collect();
+    sorted_args.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut parts = vec![dockerfile.as_bytes(), context.as_bytes()];
+    for (k, v) in &sorted_args {
+        parts.push(k.as_bytes());
+        parts.push(v.as_bytes());
+    }
+
+    format!("{}:{:016x}", BUILD_TAG_PREFIX, fnv1a(&parts))
+}
+
+/// Build `dockerfile` against `context`, passing `build_args` through as
+/// `--build-arg` flags, and return the deterministic tag the resulting
+/// image was built under so it can be used as the image to `run`.
+pub fn build_image(
+    engine: &Engine,
+    dockerfile: &Path,
+    context: &Path,
+    build_args: &[(String, String)],
+) -> Result<String, Box<dyn Error>> {
+    let preprocessed = preprocess_dockerfile(dockerfile)?;
+    let tag = tag_for(dockerfile, context, build_args);
+
+    let mut args = vec!["build".to_string()];
+    for (key, value) in build_args {
+        args.push("--build-arg".to_string());
+        args.push(format!("{}={}", key, value));
+    }
+    args.push("-f".to_string());
+    args.push("-".to_string());
+    args.push("-t".to_string());
+    args.push(tag.clone());
+    args.push(context.display().to_string());
+
+    let mut child = Command::new(engine.path())
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()?;
+
+    child
+        .stdin
+        .take()
+        .expect("child stdin was piped")
+        .write_all(preprocessed.as_bytes())?;
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(format!("`{} build` failed for {}", engine.path().display(), dockerfile.display()).into());
+    }
+
+    Ok(tag)
+}
+
+/// Interpolate each build-arg value (the repo's `shell_interpolate`, so
+/// `$(...)`/`${VAR}` references in build args resolve the same way as
+/// everywhere else in a config) and flatten the map into a list, in the
+/// map's own (unsorted) iteration order.
+pub fn interpolate_build_args(
+    build_args: &HashMap<String, String>,
+    interpolate: impl Fn(&str) -> Result<String, Box<dyn Error>>,
+) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    build_args
+        .iter()
+        .map(|(k, v)| Ok((k.clone(), interpolate(v)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    /// A fresh, unique scratch directory under the system temp dir, removed
+    /// when the guard drops.
+    struct TempDir(std::path::PathBuf);
+
+    impl TempDir {
+        fn new(label: &str) -> Self {
+            let dir = env::temp_dir().join(format!("doa-test-{}-{}", label, std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+
+        fn path(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn tag_is_stable_regardless_of_build_arg_order() {
+        let dockerfile = Path::new("Dockerfile");
+        let context = Path::new(".");
+
+        let forward = vec![
+            ("A".to_string(), "1".to_string()),
+            ("B".to_string(), "2".to_string()),
+        ];
+        let reversed = vec![
+            ("B".to_string(), "2".to_string()),
+            ("A".to_string(), "1".to_string()),
+        ];
+
+        assert_eq!(
+            tag_for(dockerfile, context, &forward),
+            tag_for(dockerfile, context, &reversed)
+        );
+    }
+
+    #[test]
+    fn splices_a_single_include() {
+        let dir = TempDir::new("single-include");
+        fs::write(dir.path().join("base.dockerfile"), "FROM alpine\nRUN base-step\n").unwrap();
+        fs::write(
+            dir.path().join("Dockerfile"),
+            "INCLUDE: base.dockerfile\nRUN app-step\n",
+        )
+        .unwrap();
+
+        let out = preprocess_dockerfile(&dir.path().join("Dockerfile")).unwrap();
+        assert_eq!(out, "FROM alpine\nRUN base-step\n\nRUN app-step\n");
+    }
+
+    #[test]
+    fn splices_nested_includes_transitively() {
+        let dir = TempDir::new("nested-include");
+        fs::write(dir.path().join("grandparent.dockerfile"), "FROM alpine\n").unwrap();
+        fs::write(
+            dir.path().join("parent.dockerfile"),
+            "INCLUDE: grandparent.dockerfile\nRUN parent-step\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("Dockerfile"),
+            "INCLUDE: parent.dockerfile\nRUN app-step\n",
+        )
+        .unwrap();
+
+        let out = preprocess_dockerfile(&dir.path().join("Dockerfile")).unwrap();
+        assert_eq!(
+            out,
+            "FROM alpine\n\nRUN parent-step\n\nRUN app-step\n"
+        );
+    }
+
+    #[test]
+    fn leaves_a_dockerfile_without_includes_untouched() {
+        let dir = TempDir::new("no-include");
+        fs::write(dir.path().join("Dockerfile"), "FROM alpine\nRUN app-step\n").unwrap();
+
+        let out = preprocess_dockerfile(&dir.path().join("Dockerfile")).unwrap();
+        assert_eq!(out, "FROM alpine\nRUN app-step\n");
+    }
+}
@@ -0,0 +1,41 @@
+use std::env;
+use std::error::Error;
+
+use crate::docker::engine::{detect_engine, Engine};
+
+/// Where a `run` will be carried out: a resolved CLI binary, or a Docker
+/// Engine API endpoint reachable over plain HTTP or a Unix socket.
+pub enum Connection {
+    Cli(Engine),
+    Api(String),
+}
+
+/// A way of locating the engine to talk to. `get_cli_path`/`detect_engine`
+/// used to be the only option; this makes it one of several, so a config
+/// can instead point at a Docker Engine API endpoint without a `docker`
+/// binary on `PATH` at all.
+pub trait ConnectionStrategy {
+    fn connect(&self) -> Result<Connection, Box<dyn Error>>;
+}
+
+/// Resolve a `docker`/`podman` CLI binary, same as before.
+pub struct CliStrategy;
+
+impl ConnectionStrategy for CliStrategy {
+    fn connect(&self) -> Result<Connection, Box<dyn Error>> {
+        Ok(Connection::Cli(detect_engine()?))
+    }
+}
+
+/// Resolve a Docker Engine API endpoint, honoring `DOCKER_HOST` and
+/// defaulting to the local Unix socket.
+pub struct ApiStrategy;
+
+impl ConnectionStrategy for ApiStrategy {
+    fn connect(&self) -> Result<Connection, Box<dyn Error>> {
+        let endpoint = env::var("DOCKER_HOST")
+            .unwrap_or_else(|_| "unix:///var/run/docker.sock".to_string());
+
+        Ok(Connection::Api(endpoint))
+    }
+}
@@ -0,0 +1,111 @@
+use std::env;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use which::which;
+
+/// Which container runtime a resolved CLI binary corresponds to.
+///
+/// This matters because Docker and Podman diverge on a handful of flags
+/// (rootless user namespaces, seccomp profiles, ...) that `DockerRun::run`
+/// needs to tailor per engine rather than assuming Docker everywhere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineKind {
+    Docker,
+    Podman,
+}
+
+/// A resolved container engine: its kind plus the path to its CLI binary.
+#[derive(Debug, Clone)]
+pub struct Engine {
+    pub kind: EngineKind,
+    pub path: PathBuf,
+}
+
+impl Engine {
+    pub fn new(kind: EngineKind, path: PathBuf) -> Self {
+        Engine { kind, path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether `--security-opt seccomp=...` is safe to emit for this engine.
+    ///
+    /// Rootless Podman manages seccomp profiles differently and rejects or
+    /// ignores some Docker-style seccomp overrides, so callers should only
+    /// add the flag on Docker.
+    pub fn supports_seccomp_opt(&self) -> bool {
+        matches!(self.kind, EngineKind::Docker)
+    }
+
+    /// Whether rootless user-namespace mapping should be requested.
+    ///
+    /// Podman on a rootless host needs `--userns=keep-id` so that files
+    /// written into bind mounts keep the invoking user's ownership.
+    pub fn supports_userns_keep_id(&self) -> bool {
+        matches!(self.kind, EngineKind::Podman)
+    }
+}
+
+/// Env var that lets users force which engine binary to use, e.g.
+/// `DOA_CONTAINER_ENGINE=podman` on a rootless Podman host. When it is set,
+/// no other binary is tried.
+pub const ENGINE_ENV_VAR: &str = "DOA_CONTAINER_ENGINE";
+
+fn kind_from_name(name: &str) -> Option<EngineKind> {
+    match name {
+        "docker" => Some(EngineKind::Docker),
+        "podman" => Some(EngineKind::Podman),
+        _ => None,
+    }
+}
+
+/// Probe the resolved binary itself by running `--version` and inspecting
+/// its output, the way cross's engine module does. This is what actually
+/// tells Docker and Podman apart: systems that install Podman's
+/// Docker-compatible CLI shim (`podman-docker`) resolve a `docker` binary
+/// on `$PATH` that is really Podman underneath, so the file name alone is
+/// not a reliable signal.
+fn probe_kind(path: &Path) -> Option<EngineKind> {
+    let output = Command::new(path).arg("--version").output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout).to_lowercase();
+
+    if text.contains("podman") {
+        Some(EngineKind::Podman)
+    } else if text.contains("docker") {
+        Some(EngineKind::Docker)
+    } else {
+        None
+    }
+}
+
+/// Resolve a binary's engine kind, preferring to probe it directly and
+/// only falling back to the requested/file name if the probe fails (e.g.
+/// the binary exists but doesn't support `--version`).
+fn resolve_kind(path: &Path, name_hint: &str) -> EngineKind {
+    probe_kind(path)
+        .or_else(|| kind_from_name(name_hint))
+        .unwrap_or(EngineKind::Docker)
+}
+
+/// Resolve the container engine to use: the binary named by
+/// `DOA_CONTAINER_ENGINE` if set (an error if it isn't on `$PATH`),
+/// otherwise `docker` and then `podman` on `$PATH`.
+pub fn detect_engine() -> Result<Engine, Box<dyn Error>> {
+    if let Ok(preferred) = env::var(ENGINE_ENV_VAR) {
+        let path = which(&preferred)?;
+        let kind = resolve_kind(&path, &preferred);
+        return Ok(Engine::new(kind, path));
+    }
+
+    let path = which("docker").or_else(|_| which("podman"))?;
+    let name_hint = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("docker");
+    let kind = resolve_kind(&path, name_hint);
+
+    Ok(Engine::new(kind, path))
+}
@@ -0,0 +1,50 @@
+use thiserror::Error;
+
+/// Structured errors for flag-building, replacing the `.expect()` panics
+/// that used to abort `run()` outright whenever a single config value
+/// failed to interpolate.
+#[derive(Debug, Error)]
+pub enum DoaError {
+    #[error("invalid `{field}` value `{raw}`: {source}")]
+    Interpolation {
+        field: &'static str,
+        raw: String,
+        #[source]
+        source: InterpolationError,
+    },
+
+    #[error("{0}")]
+    Config(String),
+}
+
+impl DoaError {
+    pub fn interpolation(field: &'static str, raw: &str, source: InterpolationError) -> Self {
+        DoaError::Interpolation {
+            field,
+            raw: raw.to_string(),
+            source,
+        }
+    }
+}
+
+/// Errors resolving `$(...)` / `${VAR}` references inside a single config
+/// value.
+#[derive(Debug, Error)]
+pub enum InterpolationError {
+    #[error("command substitution `$({cmd})` failed: {source}")]
+    CommandSubstitution {
+        cmd: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    #[error("unterminated `{opening}...{closing}` expression in `{raw}`")]
+    Unterminated {
+        raw: String,
+        opening: char,
+        closing: char,
+    },
+
+    #[error("`${{{var}}}` is not set in the config vars or the environment, and no default was given")]
+    UnresolvedVariable { var: String },
+}
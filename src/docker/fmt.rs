@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::env;
+
+use crate::docker::error::InterpolationError;
+
+/// Substitute references inside a single config value.
+///
+/// `${VAR}` / `${VAR:-default}` are resolved purely in Rust, first from
+/// `kv` (the config's own variable map) and then from the process
+/// environment, without spawning a shell. Only explicit `$(...)` command
+/// substitution still shells out, via the caller-supplied `run_cmd` -
+/// plain variable references no longer pay for a fork, and no longer risk
+/// being reinterpreted as shell syntax.
+pub fn interpolate<F>(
+    raw: &str,
+    kv: &HashMap<String, String>,
+    run_cmd: F,
+) -> Result<String, InterpolationError>
+where
+    F: Fn(&str) -> Result<String, Box<dyn std::error::Error + Send + Sync>>,
+{
+    let chars: Vec<char> = raw.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i] == '$' && chars.get(i + 1) == Some(&'(') {
+            let (expr, end) = extract_balanced(raw, &chars, i + 2, '(', ')')?;
+            let value = run_cmd(&expr).map_err(|source| {
+                InterpolationError::CommandSubstitution { cmd: expr, source }
+            })?;
+            out.push_str(&value);
+            i = end + 1;
+        } else if chars[i] == '$' && chars.get(i + 1) == Some(&'{') {
+            let (expr, end) = extract_balanced(raw, &chars, i + 2, '{', '}')?;
+            out.push_str(&resolve_var(&expr, kv)?);
+            i = end + 1;
+        } else {
+            out.push(chars[i]);
+            i += 1;
+        }
+    }
+
+    Ok(out)
+}
+
+fn extract_balanced(
+    raw: &str,
+    chars: &[char],
+    start: usize,
+    opening: char,
+    closing: char,
+) -> Result<(String, usize), InterpolationError> {
+    let mut depth = 1;
+    let mut j = start;
+
+    while j < chars.len() {
+        if chars[j] == opening {
+            depth += 1;
+        } else if chars[j] == closing {
+            depth -= 1;
+            if depth == 0 {
+                return Ok((chars[start..j].iter().collect(), j));
+            }
+        }
+        j += 1;
+    }
+
+    Err(InterpolationError::Unterminated {
+        raw: raw.to_string(),
+        opening,
+        closing,
+    })
+}
+
+fn resolve_var(expr: &str, kv: &HashMap<String, String>) -> Result<String, InterpolationError> {
+    let (name, default) = match expr.split_once(":-") {
+        Some((name, default)) => (name, Some(default)),
+        None => (expr, None),
+    };
+
+    if let Some(value) = kv.get(name) {
+        return Ok(value.clone());
+    }
+
+    if let Ok(value) = env::var(name) {
+        return Ok(value);
+    }
+
+    default
+        .map(str::to_string)
+        .ok_or_else(|| InterpolationError::UnresolvedVariable {
+            var: name.to_string(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_cmd(cmd: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        Err(format!("unexpected command substitution: {}", cmd).into())
+    }
+
+    #[test]
+    fn resolves_from_kv_before_default() {
+        let mut kv = HashMap::new();
+        kv.insert("NAME".to_string(), "world".to_string());
+
+        let out = interpolate("hello ${NAME:-stranger}", &kv, no_cmd).unwrap();
+        assert_eq!(out, "hello world");
+    }
+
+    #[test]
+    fn falls_back_to_default_when_unset() {
+        let kv = HashMap::new();
+        let out = interpolate("hello ${NAME:-stranger}", &kv, no_cmd).unwrap();
+        assert_eq!(out, "hello stranger");
+    }
+
+    #[test]
+    fn nested_default_is_used_verbatim() {
+        let kv = HashMap::new();
+        let out = interpolate("${OUTER:-${INNER:-fallback}}", &kv, no_cmd).unwrap();
+        assert_eq!(out, "${INNER:-fallback}");
+    }
+
+    #[test]
+    fn unresolved_variable_without_default_is_an_error() {
+        let kv = HashMap::new();
+        let err = interpolate("${MISSING}", &kv, no_cmd).unwrap_err();
+        assert!(matches!(err, InterpolationError::UnresolvedVariable { var } if var == "MISSING"));
+    }
+
+    #[test]
+    fn unterminated_expression_is_an_error() {
+        let kv = HashMap::new();
+        let err = interpolate("${MISSING", &kv, no_cmd).unwrap_err();
+        assert!(matches!(err, InterpolationError::Unterminated { opening, closing, .. } if opening == '{' && closing == '}'));
+    }
+
+    #[test]
+    fn command_substitution_runs_run_cmd() {
+        let kv = HashMap::new();
+        let out = interpolate("$(echo hi)", &kv, |cmd| Ok(format!("ran: {}", cmd))).unwrap();
+        assert_eq!(out, "ran: echo hi");
+    }
+}
@@ -0,0 +1,15 @@
+/// FNV-1a hash of a sequence of byte slices, used wherever this crate
+/// needs a cheap, deterministic fingerprint (volume names, build tags,
+/// container names) rather than cryptographic strength.
+pub fn fnv1a(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+
+    for part in parts {
+        for byte in *part {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    hash
+}
@@ -1,13 +1,28 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::env;
 use std::error::Error;
-use std::io::{self, Write};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
 use std::str;
-use which::which;
 
+mod api;
+mod build;
+mod connection;
+mod engine;
+mod error;
 mod fmt;
+mod hash;
+mod pty;
+mod volume;
+
+pub use connection::{ApiStrategy, CliStrategy, Connection, ConnectionStrategy};
+pub use engine::{detect_engine, Engine, EngineKind, ENGINE_ENV_VAR};
+pub use error::{DoaError, InterpolationError};
+pub use volume::{
+    create_volume, list_volumes, prune_volumes, remove_volume, remove_volumes,
+    REMOTE_ENV_VAR,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DockerRun {
@@ -26,10 +41,44 @@ pub struct DockerRun {
     pub volumes: Option<Vec<String>>,
     pub user: Option<String>,
     pub extra_flags: Option<Vec<String>>,
+
+    /// Run against a remote engine (honoring `DOCKER_HOST`) where local bind
+    /// mounts aren't reachable. Can also be toggled via `DOA_REMOTE`.
+    pub remote: Option<bool>,
+    /// Host source paths (matching entries in `volumes`) whose mirrored data
+    /// volume should survive past this run instead of being torn down.
+    pub persistent_volumes: Option<Vec<String>>,
+
+    /// Build `image` from this Dockerfile instead of pulling it. Supports an
+    /// `INCLUDE: <path>` directive for splicing in shared base stanzas.
+    pub dockerfile: Option<PathBuf>,
+    /// Build context directory, required when `dockerfile` is set.
+    pub context: Option<PathBuf>,
+    /// `--build-arg` values passed to the build, each shell-interpolated.
+    pub build_args: Option<HashMap<String, String>>,
+
+    /// Which connection strategy to run this container through. Defaults
+    /// to the CLI; `Api` talks to the daemon's HTTP socket directly and
+    /// needs no `docker`/`podman` binary on `PATH`. `Api` runs `image` as
+    /// given: it does not build from `dockerfile`, and it passes `volumes`
+    /// through as bind mounts without the `remote` volume mirroring.
+    pub backend: Option<Backend>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    Cli,
+    Api,
 }
 
-pub fn shell_interpolate(raw: &str) -> Result<String, Box<dyn Error>> {
-    fmt::shell_interpolate(raw, &|cmd| {
+/// Interpolate `raw` against `kv`, returning the structured error so call
+/// sites can attach which config field it came from.
+fn interpolate(
+    raw: &str,
+    kv: &HashMap<String, String>,
+) -> Result<String, InterpolationError> {
+    fmt::interpolate(raw, kv, |cmd| {
         let output = if cfg!(target_os = "windows") {
             Command::new("cmd").args(&["/C", cmd]).output()?
         } else {
@@ -40,105 +89,243 @@ pub fn shell_interpolate(raw: &str) -> Result<String, Box<dyn Error>> {
     })
 }
 
+/// Convenience wrapper for callers (image builds, volume mirroring) that
+/// don't need the field name attached to a failed interpolation.
+pub fn shell_interpolate(
+    raw: &str,
+    kv: &HashMap<String, String>,
+) -> Result<String, Box<dyn Error>> {
+    interpolate(raw, kv).map_err(Into::into)
+}
+
 impl DockerRun {
     pub fn run(
         &self,
-        docker_cmd: &Path,
-        _kv: &HashMap<String, String>,
-    ) -> Result<(), Box<dyn Error>> {
+        engine: &Engine,
+        kv: &HashMap<String, String>,
+    ) -> Result<i32, Box<dyn Error>> {
         // Convert all options into flags
-        let command_flags = self.command.as_ref().map_or(vec![], |cmds| {
+        let command_flags = self.command.as_ref().map_or(Ok(vec![]), |cmds| {
             cmds.iter()
                 .map(|cmd| {
-                    shell_interpolate(cmd).expect("Invalid env for cmds")
+                    interpolate(cmd, kv)
+                        .map_err(|source| DoaError::interpolation("command", cmd, source))
                 })
-                .collect()
-        });
+                .collect::<Result<Vec<_>, _>>()
+        })?;
 
         let entrypoint_flag =
-            self.entrypoint.as_ref().map_or(vec![], |entrypoint| {
-                vec![
-                    "--entrypoint".to_string(),
-                    shell_interpolate(entrypoint)
-                        .expect("Invalid env for entrypoint"),
-                ]
-            });
-
-        let envs_flags = self.envs.as_ref().map_or(vec![], |envs| {
+            self.entrypoint.as_ref().map_or(Ok(vec![]), |entrypoint| {
+                interpolate(entrypoint, kv)
+                    .map(|v| vec!["--entrypoint".to_string(), v])
+                    .map_err(|source| {
+                        DoaError::interpolation("entrypoint", entrypoint, source)
+                    })
+            })?;
+
+        let envs_flags = self.envs.as_ref().map_or(Ok(vec![]), |envs| {
             envs.iter()
-                .flat_map(|(k, v)| {
-                    vec![
-                        "-e".to_string(),
-                        shell_interpolate(&format!("{}={}", k, v))
-                            .expect("Invalid env for envs"),
-                    ]
+                .map(|(k, v)| {
+                    let raw = format!("{}={}", k, v);
+                    interpolate(&raw, kv)
+                        .map(|v| vec!["-e".to_string(), v])
+                        .map_err(|source| DoaError::interpolation("envs", &raw, source))
                 })
-                .collect()
-        });
+                .collect::<Result<Vec<_>, _>>()
+                .map(|flags| flags.concat())
+        })?;
 
         let env_file_flags =
-            self.env_file.as_ref().map_or(vec![], |env_file| {
-                vec![
-                    "--env-file".to_string(),
-                    shell_interpolate(&format!("{}", env_file.display()))
-                        .expect("Invalid env for env-file"),
-                ]
-            });
-
-        let network_flags = self.network.as_ref().map_or(vec![], |network| {
-            vec![shell_interpolate(&format!("--network={}", network))
-                .expect("Invalid env for env-file")]
-        });
-
-        let ports_flags = self.ports.as_ref().map_or(vec![], |ports| {
+            self.env_file.as_ref().map_or(Ok(vec![]), |env_file| {
+                let raw = format!("{}", env_file.display());
+                interpolate(&raw, kv)
+                    .map(|v| vec!["--env-file".to_string(), v])
+                    .map_err(|source| DoaError::interpolation("env_file", &raw, source))
+            })?;
+
+        let network_flags = self.network.as_ref().map_or(Ok(vec![]), |network| {
+            let raw = format!("--network={}", network);
+            interpolate(&raw, kv)
+                .map(|v| vec![v])
+                .map_err(|source| DoaError::interpolation("network", &raw, source))
+        })?;
+
+        let ports_flags = self.ports.as_ref().map_or(Ok(vec![]), |ports| {
             ports
                 .iter()
-                .flat_map(|port| {
-                    vec![
-                        "-p".to_string(),
-                        shell_interpolate(port).expect("Invalid env for ports"),
-                    ]
+                .map(|port| {
+                    interpolate(port, kv)
+                        .map(|v| vec!["-p".to_string(), v])
+                        .map_err(|source| DoaError::interpolation("ports", port, source))
                 })
-                .collect()
-        });
+                .collect::<Result<Vec<_>, _>>()
+                .map(|flags| flags.concat())
+        })?;
 
-        let volumes_flags = self.volumes.as_ref().map_or(vec![], |volumes| {
-            volumes
-                .iter()
-                .flat_map(|volume| {
-                    vec![
-                        "-v".to_string(),
-                        shell_interpolate(volume)
-                            .expect("Invalid env for volumes"),
-                    ]
-                })
-                .collect()
-        });
+        // Gated purely on the explicit opt-in, not on `DOCKER_HOST` being
+        // set: that env var is routinely present for perfectly-local
+        // engines (Docker Desktop contexts, Colima, rootless Podman over a
+        // unix socket) where bind mounts work fine, and switching those
+        // configs into volume-mirroring mode without being asked would be
+        // a surprising, expensive default.
+        let is_remote =
+            self.remote.unwrap_or(false) || env::var(volume::REMOTE_ENV_VAR).is_ok();
+
+        // On a remote engine, bind mounts can't see the local filesystem, so
+        // each volume entry is mirrored into a named data volume instead.
+        // The guards keep those volumes alive for the duration of `run` and
+        // remove the non-persistent ones again once it returns, even on
+        // error, since they drop at the end of this function's scope.
+        let mut volume_guards = vec![];
+        let volumes_flags: Vec<String> = if is_remote {
+            let existing = volume::list_volumes(engine)?;
+
+            self.volumes.as_ref().map_or(Ok(vec![]), |volumes| {
+                volumes
+                    .iter()
+                    .map(|volume| -> Result<Vec<String>, Box<dyn Error>> {
+                        let interpolated = shell_interpolate(volume, kv)?;
+                        let mut parts = interpolated.splitn(2, ':');
+                        let host_src = parts.next().unwrap_or("");
+                        let rest = match parts.next() {
+                            Some(rest) => rest,
+                            None => return Ok(vec!["-v".to_string(), interpolated]),
+                        };
+
+                        let volume_name =
+                            volume::volume_name_for(Path::new(host_src));
+                        let persistent = self.persistent_volumes.as_ref().map_or(
+                            Ok(false),
+                            |persistent_volumes| -> Result<bool, Box<dyn Error>> {
+                                for p in persistent_volumes {
+                                    if shell_interpolate(p, kv)? == host_src {
+                                        return Ok(true);
+                                    }
+                                }
+                                Ok(false)
+                            },
+                        )?;
 
-        let user_flags = self.user.as_ref().map_or(vec![], |user| {
-            vec![
-                "-u".to_string(),
-                shell_interpolate(user).expect("Invalid env for user"),
-            ]
-        });
+                        volume::create_volume(engine, &volume_name)?;
+                        if !existing.contains(&volume_name) {
+                            volume::copy_into_volume(
+                                engine,
+                                Path::new(host_src),
+                                &volume_name,
+                            )?;
+                        }
+
+                        volume_guards.push(volume::EphemeralVolumeGuard::new(
+                            engine,
+                            volume_name.clone(),
+                            persistent,
+                        ));
+
+                        Ok(vec!["-v".to_string(), format!("{}:{}", volume_name, rest)])
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(|flags| flags.concat())
+            })?
+        } else {
+            self.volumes.as_ref().map_or(Ok(vec![]), |volumes| {
+                volumes
+                    .iter()
+                    .map(|volume| {
+                        interpolate(volume, kv)
+                            .map(|v| vec!["-v".to_string(), v])
+                            .map_err(|source| {
+                                DoaError::interpolation("volumes", volume, source)
+                            })
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(|flags| flags.concat())
+            })?
+        };
+
+        let user_flags = self.user.as_ref().map_or(Ok(vec![]), |user| {
+            interpolate(user, kv)
+                .map(|v| vec!["-u".to_string(), v])
+                .map_err(|source| DoaError::interpolation("user", user, source))
+        })?;
 
         let extra_flags =
-            self.extra_flags.as_ref().map_or(vec![], |extra_flags| {
+            self.extra_flags.as_ref().map_or(Ok(vec![]), |extra_flags| {
                 extra_flags
                     .iter()
                     .map(|extra_flag| {
-                        shell_interpolate(extra_flag)
-                            .expect("Invalid env for extra flags")
+                        interpolate(extra_flag, kv).map_err(|source| {
+                            DoaError::interpolation("extra_flags", extra_flag, source)
+                        })
                     })
-                    .collect()
-            });
+                    .collect::<Result<Vec<_>, _>>()
+            })?;
+
+        let image = match (&self.dockerfile, &self.context) {
+            (Some(dockerfile), Some(context)) => {
+                let build_args = self.build_args.as_ref().map_or(
+                    Ok(vec![]),
+                    |build_args| {
+                        build::interpolate_build_args(build_args, |v| {
+                            shell_interpolate(v, kv)
+                        })
+                    },
+                )?;
+
+                build::build_image(engine, dockerfile, context, &build_args)?
+            }
+            (Some(_), None) => {
+                return Err(DoaError::Config(
+                    "`context` is required when `dockerfile` is set".to_string(),
+                )
+                .into())
+            }
+            (None, Some(_)) => {
+                return Err(DoaError::Config(
+                    "`dockerfile` is required when `context` is set".to_string(),
+                )
+                .into())
+            }
+            (None, None) => interpolate(&self.image, kv)
+                .map_err(|source| DoaError::interpolation("image", &self.image, source))?,
+        };
+
+        // Engine-specific defaults: rootless Podman needs `--userns=keep-id`
+        // to preserve file ownership on bind mounts, while the seccomp
+        // security default is only meaningful (and accepted) on Docker.
+        let engine_flags: Vec<String> = {
+            let mut flags = vec![];
 
-        let image = shell_interpolate(&self.image)?;
+            if engine.supports_userns_keep_id() {
+                flags.push("--userns=keep-id".to_string());
+            }
+
+            if engine.supports_seccomp_opt() {
+                flags.push("--security-opt".to_string());
+                flags.push("seccomp=unconfined".to_string());
+            }
+
+            flags
+        };
+
+        let interactive_flag = if self.interactive.unwrap_or(false) {
+            vec!["-i".to_string()]
+        } else {
+            vec![]
+        };
+
+        let tty_flag = if self.tty.unwrap_or(false) {
+            vec!["-t".to_string()]
+        } else {
+            vec![]
+        };
 
         let args = [
             // Command with default flags
             &["run".to_string()],
             &["--rm".to_string()],
+            &engine_flags[..],
+            &interactive_flag[..],
+            &tty_flag[..],
             // Optional flags
             &entrypoint_flag[..],
             &envs_flags[..],
@@ -154,16 +341,55 @@ impl DockerRun {
         ]
         .concat();
 
-        let output = Command::new(docker_cmd).args(args).output()?;
+        let mut command = Command::new(engine.path());
+        command.args(args);
+
+        // A TTY request needs a real pseudo-terminal so the child's raw
+        // output (cursor movement, progress bars, colors) reaches the
+        // user's terminal live instead of being buffered until exit.
+        if self.tty.unwrap_or(false) {
+            return pty::run_in_pty(&command);
+        }
+
+        command
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit());
 
-        io::stdout().write_all(&output.stdout)?;
-        io::stderr().write_all(&output.stderr)?;
-        Ok(())
+        let mut child = command.spawn()?;
+        let status = child.wait()?;
+
+        Ok(status.code().unwrap_or(-1))
+    }
+
+    /// Dispatch to the CLI or Engine API backend per `self.backend`
+    /// (defaulting to the CLI), so a config can select its connection
+    /// strategy per invocation.
+    pub fn run_with_backend(
+        &self,
+        kv: &HashMap<String, String>,
+    ) -> Result<i32, Box<dyn Error>> {
+        match self.backend.unwrap_or(Backend::Cli) {
+            Backend::Cli => {
+                let engine = match CliStrategy.connect()? {
+                    Connection::Cli(engine) => engine,
+                    Connection::Api(_) => unreachable!("CliStrategy only resolves to Connection::Cli"),
+                };
+                self.run(&engine, kv)
+            }
+            Backend::Api => {
+                let endpoint = match ApiStrategy.connect()? {
+                    Connection::Api(endpoint) => endpoint,
+                    Connection::Cli(_) => unreachable!("ApiStrategy only resolves to Connection::Api"),
+                };
+                api::run_blocking(&endpoint, self, kv)
+            }
+        }
     }
 }
 
-pub fn get_cli_path() -> Result<PathBuf, which::Error> {
-    which("docker")
+pub fn get_cli_path() -> Result<PathBuf, Box<dyn Error>> {
+    detect_engine().map(|engine| engine.path)
 }
 
 #[cfg(test)]
@@ -180,9 +406,17 @@ mod tests {
             entrypoint: None,
             envs: None,
             env_file: None,
+            network: None,
+            ports: None,
             volumes: None,
             user: None,
             extra_flags: None,
+            remote: None,
+            persistent_volumes: None,
+            dockerfile: None,
+            context: None,
+            build_args: None,
+            backend: None,
         }
     }
 
@@ -191,7 +425,7 @@ mod tests {
         let mut dr = make_dockerrun("clux/muslrust:stable");
         dr.command = Some(vec!["cargo".to_string(), "--version".to_string()]);
 
-        let docker_cmd = get_cli_path().unwrap();
-        dr.run(&docker_cmd, &HashMap::new()).unwrap();
+        let engine = detect_engine().unwrap();
+        dr.run(&engine, &HashMap::new()).unwrap();
     }
 }
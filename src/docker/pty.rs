@@ -0,0 +1,139 @@
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+use portable_pty::{native_pty_system, CommandBuilder, PtySize};
+
+/// Read from stdin with a short timeout instead of blocking indefinitely,
+/// so the forwarding thread can notice `stop` has been set and exit
+/// promptly once the child is done, rather than staying parked in a
+/// blocking read forever.
+#[cfg(unix)]
+fn read_stdin_with_timeout(buf: &mut [u8], timeout_ms: i32) -> io::Result<Option<usize>> {
+    use std::os::unix::io::AsRawFd;
+
+    let fd = io::stdin().as_raw_fd();
+    let mut pollfd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+
+    let ready = unsafe { libc::poll(&mut pollfd, 1, timeout_ms) };
+    if ready <= 0 {
+        return Ok(None);
+    }
+
+    io::stdin().lock().read(buf).map(Some)
+}
+
+/// No cheap portable way to poll stdin with a timeout outside Unix, so
+/// this falls back to a plain blocking read; the thread may then outlive
+/// the child on Windows until the next stdin byte arrives.
+#[cfg(not(unix))]
+fn read_stdin_with_timeout(buf: &mut [u8], _timeout_ms: i32) -> io::Result<Option<usize>> {
+    io::stdin().lock().read(buf).map(Some)
+}
+
+/// Run `command` attached to a freshly allocated pseudo-terminal, forwarding
+/// the user's own stdin/stdout live (raw mode) so interactive shells,
+/// progress bars and colored output behave as if run directly.
+///
+/// Returns the child's exit code.
+pub fn run_in_pty(command: &Command) -> Result<i32, Box<dyn Error>> {
+    let pty_system = native_pty_system();
+
+    let (cols, rows) = terminal_size();
+    let pair = pty_system.openpty(PtySize {
+        rows,
+        cols,
+        pixel_width: 0,
+        pixel_height: 0,
+    })?;
+
+    let mut builder = CommandBuilder::new(command.get_program());
+    builder.args(command.get_args());
+    for (key, value) in command.get_envs() {
+        if let Some(value) = value {
+            builder.env(key, value);
+        }
+    }
+
+    let mut child = pair.slave.spawn_command(builder)?;
+    drop(pair.slave);
+
+    let mut reader = pair.master.try_clone_reader()?;
+    let mut writer = pair.master.take_writer()?;
+
+    let raw_guard = crossterm::terminal::enable_raw_mode();
+
+    let (done_tx, done_rx) = mpsc::channel();
+    let copy_out = thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        let stdout = io::stdout();
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    let mut handle = stdout.lock();
+                    if handle.write_all(&buf[..n]).is_err() {
+                        break;
+                    }
+                    let _ = handle.flush();
+                }
+            }
+        }
+        let _ = done_tx.send(());
+    });
+
+    let stop_stdin = Arc::new(AtomicBool::new(false));
+    let stop_stdin_for_thread = Arc::clone(&stop_stdin);
+    let copy_in = thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        while !stop_stdin_for_thread.load(Ordering::Relaxed) {
+            let n = match read_stdin_with_timeout(&mut buf, 100) {
+                Ok(None) => continue,
+                Ok(Some(0)) | Err(_) => break,
+                Ok(Some(n)) => n,
+            };
+            if writer.write_all(&buf[..n]).is_err() {
+                break;
+            }
+        }
+    });
+
+    let exit_status = child.wait()?;
+    let _ = done_rx.recv();
+    let _ = copy_out.join();
+
+    // Signal the stdin-forwarding thread to stop now that the child is
+    // gone, rather than leaving it parked on a blocking read forever.
+    stop_stdin.store(true, Ordering::Relaxed);
+
+    // On unix the timeout poll above guarantees `copy_in` wakes up and
+    // notices `stop_stdin` within ~100ms, so it's safe (and worth it, to
+    // avoid leaking a dangling handle) to wait for it here. Off unix there's
+    // no timeout, so it may stay blocked on a read until the next stdin
+    // byte arrives - joining here would hang `run_in_pty` well after the
+    // child is gone, so just let it run down on its own instead.
+    #[cfg(unix)]
+    let _ = copy_in.join();
+    #[cfg(not(unix))]
+    drop(copy_in);
+
+    if raw_guard.is_ok() {
+        let _ = crossterm::terminal::disable_raw_mode();
+    }
+
+    Ok(exit_status.exit_code() as i32)
+}
+
+fn terminal_size() -> (u16, u16) {
+    crossterm::terminal::size()
+        .map(|(cols, rows)| (cols, rows))
+        .unwrap_or((80, 24))
+}
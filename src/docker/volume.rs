@@ -0,0 +1,152 @@
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+
+use crate::docker::hash::fnv1a;
+use crate::docker::Engine;
+
+/// Prefix of every volume name doa derives, so its volumes are easy to
+/// recognize on a shared remote host. `list-volumes` / `remove-volumes` /
+/// `prune-volumes` find (and only touch) doa's own volumes by the
+/// `doa=true` label that `create_volume` attaches, not by this prefix.
+pub const VOLUME_NAME_PREFIX: &str = "doa-";
+
+/// Env var toggling remote-host execution, where bind mounts from the
+/// local filesystem aren't reachable by the engine and data has to be
+/// mirrored into named volumes instead.
+pub const REMOTE_ENV_VAR: &str = "DOA_REMOTE";
+
+/// Derive a stable, doa-prefixed volume name for a local bind-mount source
+/// path, so re-running the same config against a remote host reuses the
+/// same named volume instead of minting a new one each time.
+pub fn volume_name_for(source: &Path) -> String {
+    let hash = fnv1a(&[source.to_string_lossy().as_bytes()]);
+    format!("{}{:016x}", VOLUME_NAME_PREFIX, hash)
+}
+
+fn run_checked(engine: &Engine, args: &[&str]) -> Result<String, Box<dyn Error>> {
+    let output = Command::new(engine.path()).args(args).output()?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "`{} {}` failed: {}",
+            engine.path().display(),
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+}
+
+/// Create a named data volume if it doesn't already exist.
+pub fn create_volume(engine: &Engine, name: &str) -> Result<(), Box<dyn Error>> {
+    run_checked(
+        engine,
+        &["volume", "create", "--label", "doa=true", name],
+    )?;
+    Ok(())
+}
+
+/// Remove a single named data volume.
+pub fn remove_volume(engine: &Engine, name: &str) -> Result<(), Box<dyn Error>> {
+    run_checked(engine, &["volume", "rm", "-f", name])?;
+    Ok(())
+}
+
+/// List the names of every volume doa created (tagged with the `doa=true`
+/// label), as opposed to every volume on the host.
+pub fn list_volumes(engine: &Engine) -> Result<Vec<String>, Box<dyn Error>> {
+    let out = run_checked(
+        engine,
+        &["volume", "ls", "--filter", "label=doa=true", "--format", "{{.Name}}"],
+    )?;
+
+    Ok(out.lines().map(str::to_string).collect())
+}
+
+/// Remove every volume doa created.
+pub fn remove_volumes(engine: &Engine) -> Result<(), Box<dyn Error>> {
+    for name in list_volumes(engine)? {
+        remove_volume(engine, &name)?;
+    }
+    Ok(())
+}
+
+/// Remove every doa-created volume that isn't referenced by a running or
+/// stopped container.
+pub fn prune_volumes(engine: &Engine) -> Result<(), Box<dyn Error>> {
+    run_checked(
+        engine,
+        &["volume", "prune", "-f", "--filter", "label=doa=true"],
+    )?;
+    Ok(())
+}
+
+/// Seed a named volume with the contents of a local directory by creating a
+/// short-lived helper container with the volume mounted and copying
+/// `source` into it with `cp`, which streams the files from the local side
+/// (a bind mount would resolve `source` on the engine's host instead).
+pub fn copy_into_volume(
+    engine: &Engine,
+    source: &Path,
+    volume_name: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mount = format!("{}:/doa-dst", volume_name);
+    let helper = run_checked(engine, &["create", "-v", &mount, "alpine", "true"])?;
+
+    let src = format!("{}/.", source.display());
+    let dst = format!("{}:/doa-dst/", helper);
+    let copied = run_checked(engine, &["cp", &src, &dst]);
+    let _ = run_checked(engine, &["rm", "-f", &helper]);
+    copied?;
+
+    Ok(())
+}
+
+/// RAII guard for a volume created to stand in for a bind mount during a
+/// single `run`. Unless marked persistent, the volume is removed when the
+/// guard drops, including on the error path back out of `run`.
+pub struct EphemeralVolumeGuard<'a> {
+    engine: &'a Engine,
+    name: String,
+    persistent: bool,
+}
+
+impl<'a> EphemeralVolumeGuard<'a> {
+    pub fn new(engine: &'a Engine, name: String, persistent: bool) -> Self {
+        EphemeralVolumeGuard {
+            engine,
+            name,
+            persistent,
+        }
+    }
+}
+
+impl<'a> Drop for EphemeralVolumeGuard<'a> {
+    fn drop(&mut self) {
+        if !self.persistent {
+            let _ = remove_volume(self.engine, &self.name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volume_name_is_prefixed_and_stable() {
+        let name = volume_name_for(Path::new("/home/user/project"));
+        assert!(name.starts_with(VOLUME_NAME_PREFIX));
+        assert_eq!(name, volume_name_for(Path::new("/home/user/project")));
+    }
+
+    #[test]
+    fn volume_name_differs_by_source() {
+        let a = volume_name_for(Path::new("/home/user/project-a"));
+        let b = volume_name_for(Path::new("/home/user/project-b"));
+        assert_ne!(a, b);
+    }
+}